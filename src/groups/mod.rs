@@ -11,9 +11,17 @@ pub enum ErrorKind {
     InternalError,
     VoiceNotEnabled,
     MustProvideURL,
+    MustProvideSomeArguments(usize),
     MusicDataNotInitialized,
     MessageNotInGuildChannel,
-    NoSongCurrentlyPlaying
+    NoSongCurrentlyPlaying,
+    QueueIsEmpty,
+    QueueIndexOutOfBounds,
+    NoMatchesFound,
+    FavoriteNotFound,
+    BackendUnavailable,
+    FileNotFound,
+    SpotifyNotConfigured
 }
 
 pub type GuildMusicResult<T> = Result<T, DiscordCommandError>;
@@ -0,0 +1,74 @@
+use std::{path::PathBuf, time::Duration};
+
+use async_trait::async_trait;
+use serenity::model::id::{ChannelId, GuildId};
+use uuid::Uuid;
+
+/// Where a backend should actually read a track's audio from.
+///
+/// Remote backends (Lavalink) only ever deal in `Url`; the songbird backend additionally
+/// supports `File` for locally stored/downloaded attachments, decoded through symphonia
+/// instead of handed off to yt-dlp.
+#[derive(Debug, Clone)]
+pub enum TrackSource {
+    Url(String),
+    File(PathBuf),
+}
+
+/// Metadata for a single track, returned by [`PlaybackBackend::resolve`].
+///
+/// `search_or_url` is whatever the backend needs to actually start playback later
+/// (the URL or search term for the songbird backend, an encoded track identifier for
+/// Lavalink).
+#[derive(Debug, Clone)]
+pub struct ResolvedTrack {
+    pub search_or_url: String,
+    pub source: TrackSource,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub duration: Option<Duration>,
+    pub thumbnail: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum BackendError {
+    NoMatches,
+    NotInVoiceChannel,
+    ConnectionFailed(String),
+}
+
+/// Abstracts over where audio is actually decoded/streamed from, so `GuildMusic` can stay
+/// agnostic between driving songbird's in-process player and forwarding playback to a
+/// Lavalink node.
+#[async_trait]
+pub trait PlaybackBackend: Send + Sync {
+    /// Short, human-readable name surfaced by the `backend` command, so it's obvious at
+    /// runtime whether `MUSIC_BACKEND` actually took effect.
+    fn name(&self) -> &'static str;
+
+    /// Look up a search term or URL and return every track it expands to (a single entry
+    /// for a plain track, many for a playlist).
+    async fn resolve(&self, search_or_url: &str) -> Result<Vec<ResolvedTrack>, BackendError>;
+
+    /// Start playing `track` for `guild_id`, joining `channel_id` if not already connected.
+    /// `music_uuid` must be threaded back through to `GuildMusic::handle_track_event` once
+    /// the track naturally ends.
+    async fn play(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        track: &ResolvedTrack,
+        music_uuid: Uuid,
+    ) -> Result<(), BackendError>;
+
+    /// Stop whatever is currently playing for `guild_id`, if anything.
+    async fn stop(&self, guild_id: GuildId);
+
+    /// Current playback position of the track, if one is playing.
+    async fn position(&self, guild_id: GuildId) -> Option<Duration>;
+
+    /// Optional hint to start preparing `track` ahead of time so `play` can start it with
+    /// no further network/decoding latency. Backends that have nothing to gain from this
+    /// (e.g. Lavalink, which resolves to an already-loadable identifier) can ignore it.
+    async fn preload(&self, _guild_id: GuildId, _track: &ResolvedTrack) {}
+}
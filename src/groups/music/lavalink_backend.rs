@@ -0,0 +1,174 @@
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use lavalink_rs::{
+    gateway::LavalinkEventHandler,
+    model::{Track as LavalinkTrack, TrackFinish},
+    LavalinkClient,
+};
+use log::warn;
+use parking_lot::Mutex;
+use serenity::{
+    model::id::{ChannelId, GuildId},
+    prelude::Mutex as AsyncMutex,
+};
+use songbird::Call;
+use uuid::Uuid;
+
+use crate::SONGBIRD;
+
+use super::{
+    backend::{BackendError, PlaybackBackend, ResolvedTrack, TrackSource},
+    TrackEventListener,
+};
+
+const LAVALINK_HOST_ENVIRONMENT_VARIABLE: &str = "LAVALINK_HOST";
+const LAVALINK_PASSWORD_ENVIRONMENT_VARIABLE: &str = "LAVALINK_PASSWORD";
+
+/// Maps a Lavalink track identifier back to the `music_uuid` `GuildMusic` expects in its
+/// `TrackEvent::End` handling, since the Lavalink websocket event only carries the former.
+type TrackUuidMap = Arc<Mutex<HashMap<(GuildId, String), Uuid>>>;
+
+struct LavalinkHandler {
+    track_uuids: TrackUuidMap,
+}
+
+#[async_trait]
+impl LavalinkEventHandler for LavalinkHandler {
+    async fn track_finish(&self, _client: LavalinkClient, event: TrackFinish) {
+        let guild_id = GuildId(event.guild_id.0);
+        let music_uuid = self
+            .track_uuids
+            .lock()
+            .remove(&(guild_id, event.track.clone()));
+        if let Some(music_uuid) = music_uuid {
+            // Reuses the same dispatch path the songbird backend's global track-end event
+            // uses, so `GuildMusic` never has to know which backend fired the callback.
+            TrackEventListener { guild_id, music_uuid }.fire();
+        };
+    }
+}
+
+/// Drives playback through a standalone Lavalink node over `lavalink-rs`, so audio
+/// decoding happens off-process instead of inside this bot.
+pub struct LavalinkBackend {
+    client: LavalinkClient,
+    track_uuids: TrackUuidMap,
+    /// Guilds that already have a songbird voice connection registered with the Lavalink
+    /// node. `lavalink-rs` only receives audio once a `Call` exists and its voice
+    /// state/server updates have been forwarded to the node via
+    /// `create_session_with_songbird`, so this has to happen before the first `play`.
+    joined: Mutex<HashMap<GuildId, Arc<AsyncMutex<Call>>>>,
+}
+
+impl LavalinkBackend {
+    pub async fn connect(bot_id: u64, bot_token: &str) -> Result<Self, BackendError> {
+        let host = env::var(LAVALINK_HOST_ENVIRONMENT_VARIABLE).unwrap_or_else(|_| "127.0.0.1:2333".to_string());
+        let password = env::var(LAVALINK_PASSWORD_ENVIRONMENT_VARIABLE).unwrap_or_else(|_| "youshallnotpass".to_string());
+        let track_uuids: TrackUuidMap = Arc::new(Mutex::new(HashMap::new()));
+
+        let client = LavalinkClient::builder(bot_id)
+            .set_host(host)
+            .set_password(password)
+            .set_token(bot_token)
+            .build(LavalinkHandler { track_uuids: track_uuids.clone() })
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+
+        Ok(Self { client, track_uuids, joined: Mutex::new(HashMap::new()) })
+    }
+
+    /// Joins `channel_id` through songbird (the same gateway join path the songbird backend
+    /// uses) and hands the resulting voice connection to Lavalink, so the node actually has
+    /// a session to stream audio into. A no-op once a guild is already joined.
+    async fn ensure_joined(&self, guild_id: GuildId, channel_id: ChannelId) -> Result<(), BackendError> {
+        if self.joined.lock().contains_key(&guild_id) {
+            return Ok(());
+        };
+        let (call_lock, result) = SONGBIRD.join(guild_id, channel_id).await;
+        result.map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+        self.client
+            .create_session_with_songbird(&call_lock)
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+        self.joined.lock().insert(guild_id, call_lock);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PlaybackBackend for LavalinkBackend {
+    fn name(&self) -> &'static str {
+        "lavalink"
+    }
+
+    async fn resolve(&self, search_or_url: &str) -> Result<Vec<ResolvedTrack>, BackendError> {
+        let query = if search_or_url.starts_with("http") {
+            search_or_url.to_string()
+        } else {
+            format!("ytsearch:{search_or_url}")
+        };
+        let loaded = self
+            .client
+            .get_tracks(query)
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+        let mut tracks: Vec<ResolvedTrack> = loaded
+            .tracks
+            .into_iter()
+            .map(|track: LavalinkTrack| ResolvedTrack {
+                source: TrackSource::Url(track.track.clone()),
+                search_or_url: track.track,
+                title: track.info.as_ref().map(|info| info.title.clone()),
+                author: track.info.as_ref().map(|info| info.author.clone()),
+                duration: track.info.as_ref().map(|info| Duration::from_millis(info.length)),
+                thumbnail: None,
+            })
+            .collect();
+        // A search yields every match; only the top result is queued, like a plain track.
+        if !search_or_url.starts_with("http") {
+            tracks.truncate(1);
+        };
+        if tracks.is_empty() {
+            Err(BackendError::NoMatches)
+        } else {
+            Ok(tracks)
+        }
+    }
+
+    async fn play(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        track: &ResolvedTrack,
+        music_uuid: Uuid,
+    ) -> Result<(), BackendError> {
+        self.ensure_joined(guild_id, channel_id).await?;
+        self.track_uuids
+            .lock()
+            .insert((guild_id, track.search_or_url.clone()), music_uuid);
+        self.client
+            .play(guild_id.0, lavalink_rs::model::Track { track: track.search_or_url.clone(), info: None })
+            .queue()
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))
+    }
+
+    async fn stop(&self, guild_id: GuildId) {
+        // Only halts the current track, the same as `SongbirdBackend::stop`. `stop` is also
+        // what `skip` calls between tracks, so disconnecting here would drop the bot from
+        // voice on every skip; this crate has no voice-leave path for either backend, so
+        // staying joined here keeps the two backends' behavior consistent.
+        if let Err(why) = self.client.stop(guild_id.0).await {
+            warn!("Failed to stop Lavalink playback for guild {}: {}", guild_id, why);
+        };
+    }
+
+    async fn position(&self, guild_id: GuildId) -> Option<Duration> {
+        self.client
+            .nodes()
+            .await
+            .get(&guild_id.0)
+            .map(|node| Duration::from_millis(node.now_playing.as_ref().map_or(0, |np| np.info.as_ref().map_or(0, |i| i.position))))
+    }
+}
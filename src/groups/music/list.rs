@@ -1,17 +1,26 @@
 use std::collections::VecDeque;
 
-use rand::{Rng, thread_rng, distributions::Uniform};
+use rand::{thread_rng, Rng};
 
 pub trait Shuffleable {
+    /// Randomizes order, leaving the front entry (the one the backend may already be
+    /// preloading) in place so an in-flight preload doesn't go to waste.
     fn shuffle(&mut self) -> &Self;
 }
 
 impl<T> Shuffleable for VecDeque<T> {
+    /// Durstenfeld's variant of Fisher-Yates over the `[1, len)` sub-range: each step's
+    /// sampling range shrinks with `i`, which is what makes every permutation equally
+    /// likely. Sampling a fixed `[1, len)` range every iteration (as a naive reading of
+    /// Fisher-Yates tends to produce) is biased.
     fn shuffle(&mut self) -> &Self {
-        let mut rng = thread_rng();
-        let uniform = Uniform::<usize>::new(0, self.len());
-        for i in 0..self.len() {
-            self.swap(i, rng.sample(uniform));
+        let len = self.len();
+        if len > 2 {
+            let mut rng = thread_rng();
+            for i in (2..len).rev() {
+                let j = rng.gen_range(1..=i);
+                self.swap(i, j);
+            };
         };
         self
     }
@@ -25,4 +34,19 @@ fn shuffle_bound() {
     for _ in 0..10516 {
         vd.shuffle();
     }
+}
+
+#[test]
+fn shuffle_preserves_the_front_entry_and_every_other_element() {
+    let original: VecDeque<usize> = (0..8).collect();
+    for _ in 0..100 {
+        let mut vd = original.clone();
+        vd.shuffle();
+        assert_eq!(vd[0], original[0], "the front entry must stay in place");
+        let mut shuffled: Vec<_> = vd.into_iter().collect();
+        let mut expected: Vec<_> = original.iter().copied().collect();
+        shuffled.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(shuffled, expected, "shuffle must not add, drop, or duplicate entries");
+    }
 }
\ No newline at end of file
@@ -1,10 +1,21 @@
 pub mod list;
 
-use std::{time::Duration, collections::VecDeque};
+mod backend;
+mod lavalink_backend;
+mod songbird_backend;
+mod spotify;
+mod store;
+
+use std::{time::Duration, collections::VecDeque, env, path::{Path, PathBuf}};
 use self::list::Shuffleable;
+pub use self::backend::{BackendError, PlaybackBackend, ResolvedTrack, TrackSource};
+use self::lavalink_backend::LavalinkBackend;
+use self::songbird_backend::SongbirdBackend;
+use self::spotify::SpotifyClient;
+use self::store::MusicStore;
 
 use super::{DiscordCommandError, GuildMusicResult};
-use crate::{utils::{ArcMut, reply_with_result}, SONGBIRD};
+use crate::utils::{ArcMut, reply_with_embed, reply_with_result};
 use log::{debug, warn};
 use parking_lot::Mutex;
 use serenity::{
@@ -15,25 +26,41 @@ use serenity::{
         Args, CommandResult
     },
     model::{
-        channel::Message,
+        channel::{Attachment, Message},
         id::{ChannelId, GuildId, UserId}, guild,
     },
-    prelude::{Mutex as AsyncMutex, TypeMapKey},
-};
-use songbird::{
-    create_player,
-    input::Restartable,
-    tracks::{Track, TrackHandle},
-    Call, Event, EventContext, EventHandler as VoiceEventHandler,
-    TrackEvent,
-    input::error::Error as SongbirdInputError
+    prelude::TypeMapKey,
 };
+use songbird::{Event, EventContext, EventHandler as VoiceEventHandler};
 use url::Url;
 use uuid::Uuid;
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{collections::HashMap, sync::Arc};
+
+const MUSIC_BACKEND_ENVIRONMENT_VARIABLE: &str = "MUSIC_BACKEND";
+pub(crate) const MAX_PLAYLIST_TRACKS: usize = 100;
+const LOCAL_FILE_DIRECTORY: &str = "music_files";
+
+/// What `insert_music` actually queued, so the `play` command can word its confirmation
+/// differently for a single track versus a playlist.
+pub enum QueuedSummary {
+    Track { title: String, position: usize },
+    Playlist { track_count: usize },
+}
+
+/// Which end of the queue `resolve_and_insert` should land resolved tracks on.
+enum InsertPosition {
+    Back,
+    Front,
+}
+
+/// One row of the `queue` command's embed.
+pub struct QueueEntry {
+    pub title: String,
+    pub requested_by: UserId,
+    pub duration: Option<Duration>,
+}
 
 struct GuildMusicHashMapKey;
-type TrackPair = (Track, TrackHandle);
 type GuildMusicHashmap = HashMap<GuildId, Arc<GuildMusic>>;
 
 impl TypeMapKey for GuildMusicHashMapKey {
@@ -43,210 +70,331 @@ impl TypeMapKey for GuildMusicHashMapKey {
 lazy_static! {
     static ref GUILD_MUSIC_HASHMAP: ArcMut<GuildMusicHashmap> =
         Arc::new(Mutex::new(HashMap::new()));
+    static ref BACKEND: ArcMut<Option<Arc<dyn PlaybackBackend>>> = Arc::new(Mutex::new(None));
+    static ref SPOTIFY: Option<SpotifyClient> = SpotifyClient::from_env();
+}
+
+/// Connects the backend selected by `MUSIC_BACKEND` (`lavalink` or the default `songbird`)
+/// and stores it for every `GuildMusic` created afterwards. Must be called once during
+/// startup, before the client starts dispatching commands.
+pub async fn init_backend(bot_id: u64, bot_token: &str) {
+    let backend: Arc<dyn PlaybackBackend> = match env::var(MUSIC_BACKEND_ENVIRONMENT_VARIABLE).as_deref() {
+        Ok("lavalink") => match LavalinkBackend::connect(bot_id, bot_token).await {
+            Ok(lavalink_backend) => Arc::new(lavalink_backend),
+            Err(why) => {
+                warn!("Failed to connect to Lavalink ({:?}), falling back to the songbird backend", why);
+                Arc::new(SongbirdBackend::default())
+            }
+        },
+        _ => Arc::new(SongbirdBackend::default()),
+    };
+    *BACKEND.lock() = Some(backend);
+}
+
+fn current_backend() -> Arc<dyn PlaybackBackend> {
+    BACKEND
+        .lock()
+        .clone()
+        .unwrap_or_else(|| Arc::new(SongbirdBackend::default()))
 }
 
 #[derive(Debug)]
 struct MusicRequest {
     uuid: uuid::Uuid,
     search_or_url: String,
+    source: TrackSource,
     channel_id: ChannelId,
-    _requested_by: UserId,
-    _title: Option<String>,
-    _author: Option<String>,
-    _duration: Option<Duration>
+    requested_by: UserId,
+    title: Option<String>,
+    author: Option<String>,
+    duration: Option<Duration>,
+    thumbnail: Option<String>,
 }
 
-#[derive(Debug)]
-struct LoadedMusic {
-    uuid: Uuid,
-    track_pair: Option<TrackPair>
+impl MusicRequest {
+    fn display_name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.search_or_url)
+    }
+
+    fn as_resolved_track(&self) -> ResolvedTrack {
+        ResolvedTrack {
+            search_or_url: self.search_or_url.clone(),
+            source: self.source.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            duration: self.duration,
+            thumbnail: self.thumbnail.clone(),
+        }
+    }
 }
 
 #[derive(Debug)]
 struct NowPlaying {
-    uuid: Uuid,
-    track_handle: TrackHandle
+    request: MusicRequest,
+}
+
+/// How the queue should behave once the current `now_playing` finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    Off,
+    Track,
+    Queue,
+}
+
+impl RepeatMode {
+    fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::Track,
+            RepeatMode::Track => RepeatMode::Queue,
+            RepeatMode::Queue => RepeatMode::Off,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::Track => "current track",
+            RepeatMode::Queue => "whole queue",
+        }
+    }
+}
+
+impl Default for RepeatMode {
+    fn default() -> Self {
+        RepeatMode::Off
+    }
 }
 
 #[derive(Debug)]
 pub struct GuildMusicData {
     guild_id: GuildId,
     queue: VecDeque<MusicRequest>,
-    preloaded: Option<LoadedMusic>,
     now_playing: Option<NowPlaying>,
-    call_pair: Option<(ChannelId, Arc<AsyncMutex<Call>>)>,
+    repeat_mode: RepeatMode,
 }
 
 #[allow(clippy::module_name_repetitions)]
 pub struct GuildMusic {
     pub guild_music_data: Arc<Mutex<GuildMusicData>>,
-}
-
-async fn get_ytdl_track(search_or_url: String) -> Option<TrackPair> {
-    let restartable = {
-        if let Ok(url) = search_or_url.parse::<Url>() {
-            Restartable::ytdl(url, false).await
-        } else {
-            Restartable::ytdl_search(search_or_url, false).await
-        }
-    };
-    restartable.map(|restartable| create_player(restartable.into())).ok()
-}
-
-async fn get_call(guild_id: GuildId, channel_id: ChannelId) -> Option<Arc<AsyncMutex<Call>>> {
-    let (call_lock, result) = SONGBIRD.join(guild_id, channel_id).await;
-    match result {
-        Ok(_channel) => {
-            Some(call_lock)
-        },
-        Err(err) => {
-            warn!("Error is {}, leave server {}, should reconnect {}", err, err.should_leave_server(), err.should_reconnect_driver());
-            None
-        }
-    }
+    backend: Arc<dyn PlaybackBackend>,
+    store: Arc<MusicStore>,
 }
 
 impl GuildMusic {
     pub fn new(guild_id: GuildId) -> Self {
         Self {
             guild_music_data: Arc::new(Mutex::new(GuildMusicData::new(guild_id))),
+            backend: current_backend(),
+            store: Arc::new(MusicStore::load(guild_id)),
         }
     }
 
     fn tick_guild_music(&self) {
-        let mut guild_music_data = self.guild_music_data.lock();
-        if let Some((guild_id, next, track_pair_option, current_call_option)) = {
-            if guild_music_data.now_playing.is_none() {
-                if let Some(next) = guild_music_data.queue.pop_front() {
-                    debug!("Next is {:?}", next);
-                    let track_pair = guild_music_data.preloaded.take().and_then(|preloaded| {
-                        preloaded.track_pair.and_then(|track_pair| {
-                            if preloaded.uuid == next.uuid {
-                                Some(track_pair)
-                            } else {
-                                None
-                            }
-                        })
-                    });
-                    let current_call = guild_music_data.call_pair.as_ref().and_then(|(channel_id, current_call_lock)| {
-                        if channel_id.0 == next.channel_id.0 {
-                            Some(current_call_lock.clone())
-                        } else {
-                            None
-                        }
-                    });
-                    Some((guild_music_data.guild_id, next, track_pair, current_call))
-                } else {
-                    None
-                }
+        let (guild_id, next) = {
+            let mut guild_music_data = self.guild_music_data.lock();
+            let next = if guild_music_data.now_playing.is_none() {
+                guild_music_data.queue.pop_front()
             } else {
                 None
-            }
-        } {
+            };
+            (guild_music_data.guild_id, next)
+        };
+        if let Some(next) = next {
+            debug!("Next is {:?}", next);
+            let resolved = next.as_resolved_track();
+            let backend = self.backend.clone();
+            let store = self.store.clone();
             let play_guild_music_data_lock = self.guild_music_data.clone();
+            let music_uuid = next.uuid;
+            let channel_id = next.channel_id;
             tokio::spawn(async move {
-                match {
-                    if track_pair_option.is_some() {
-                        track_pair_option
-                    } else {
-                        get_ytdl_track(next.search_or_url).await
-                    }
-                } {
-                    Some((track, track_handle)) => {
-                        match {
-                            if current_call_option.is_some() {
-                                current_call_option
-                            } else {
-                                get_call(guild_id, next.channel_id).await
-                            }
-                        } {
-                            Some(call_lock) => {
-                                {
-                                    play_guild_music_data_lock.lock().now_playing = Some(NowPlaying { uuid: next.uuid, track_handle });
-                                }
-                                let elapsed = Instant::now();
-                                let mut call = call_lock.lock().await;
-                                debug!("Spent {}ms waiting for call lock", elapsed.elapsed().as_millis());
-                                call.play(track);
-                                call.remove_all_global_events();
-                                call.add_global_event(
-                                    Event::Track(TrackEvent::End),
-                                    TrackEventListener { guild_id, music_uuid: next.uuid }
-                                );
-                            }
-                            None => {
-                                warn!("Failed to get voice channel");
-                                // reply with failure to get voice channel
-                            }
-                        };
-                    }
-                    None => {
-                        warn!("Failed to get track");
-                        // reply with failure
+                match backend.play(guild_id, channel_id, &resolved, music_uuid).await {
+                    Ok(()) => {
+                        store.record_played(resolved.search_or_url.clone(), resolved.title.clone());
+                        play_guild_music_data_lock.lock().now_playing = Some(NowPlaying { request: next });
                     }
+                    Err(why) => warn!("Failed to start playback for guild {}: {:?}", guild_id, why),
                 }
             });
         };
-        let music_to_preload = guild_music_data.queue.get(0).map(|music_request| {
-            (music_request.uuid, music_request.search_or_url.clone())
-        });
-        if let Some((uuid, _url)) = &music_to_preload {
-            guild_music_data.preloaded = Some(LoadedMusic { uuid: *uuid, track_pair: None });
-        };
-        if let Some((uuid, url)) = music_to_preload {
-            let preload_guild_music_data_lock = self.guild_music_data.clone();
+        let next_up = self.guild_music_data.lock().queue.get(0).map(MusicRequest::as_resolved_track);
+        if let Some(resolved) = next_up {
+            let backend = self.backend.clone();
             tokio::spawn(async move {
-                debug!("Preloading for uuid {} and url {}", uuid, url);
-                let time_elapsed = Instant::now();
-                if let Some(track_pair) = get_ytdl_track(url).await {
-                    debug!("Finished preloading for uuid {} in {}ms", uuid, time_elapsed.elapsed().as_millis());
-                    if let Some(ref mut preloaded) = preload_guild_music_data_lock.lock().preloaded {
-                        if preloaded.uuid == uuid {
-                            preloaded.track_pair = Some(track_pair);
-                        };
-                    };
-                };
+                debug!("Preloading {} for guild {}", resolved.search_or_url, guild_id);
+                backend.preload(guild_id, &resolved).await;
             });
         };
     }
 
-    pub fn insert_music(&self, search_or_url: String, channel_id: ChannelId, requested_by: UserId) {
-        {
+    pub async fn insert_music(
+        &self,
+        search_or_url: String,
+        channel_id: ChannelId,
+        requested_by: UserId,
+    ) -> GuildMusicResult<QueuedSummary> {
+        self.resolve_and_insert(search_or_url, channel_id, requested_by, InsertPosition::Back).await
+    }
+
+    /// Like `insert_music`, but pushes to the *front* of the queue so the track(s) play
+    /// right after whatever is currently playing. `tick_guild_music`'s preload peek at
+    /// `queue.get(0)` picks this up automatically on the next tick, retargeting prefetch to
+    /// whatever just became the new head.
+    pub async fn insert_music_next(
+        &self,
+        search_or_url: String,
+        channel_id: ChannelId,
+        requested_by: UserId,
+    ) -> GuildMusicResult<QueuedSummary> {
+        self.resolve_and_insert(search_or_url, channel_id, requested_by, InsertPosition::Front).await
+    }
+
+    /// Shared resolve/validate/truncate/insert body for `insert_music` and
+    /// `insert_music_next`, which only differ in which end of the queue they land on.
+    async fn resolve_and_insert(
+        &self,
+        search_or_url: String,
+        channel_id: ChannelId,
+        requested_by: UserId,
+        position: InsertPosition,
+    ) -> GuildMusicResult<QueuedSummary> {
+        let mut resolved_tracks = self
+            .backend
+            .resolve(&search_or_url)
+            .await
+            .map_err(|why| match why {
+                BackendError::NoMatches => DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::NoMatchesFound },
+                _ => DiscordCommandError { source: None, severity: super::ErrorSeverity::Internal, kind: super::ErrorKind::BackendUnavailable },
+            })?;
+        if resolved_tracks.is_empty() {
+            return Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::NoMatchesFound });
+        };
+        if resolved_tracks.len() > MAX_PLAYLIST_TRACKS {
+            debug!("Truncating playlist from {} to {} tracks", resolved_tracks.len(), MAX_PLAYLIST_TRACKS);
+            resolved_tracks.truncate(MAX_PLAYLIST_TRACKS);
+        };
+        let track_count = resolved_tracks.len();
+        let summary = {
+            let mut guild_music_data = self.guild_music_data.lock();
+            let first_title = resolved_tracks[0].title.clone().unwrap_or_else(|| resolved_tracks[0].search_or_url.clone());
+            let queue_position = match position {
+                InsertPosition::Back => guild_music_data.queue.len() + 1,
+                InsertPosition::Front => 1,
+            };
+            match position {
+                InsertPosition::Back => {
+                    for resolved in resolved_tracks {
+                        guild_music_data.queue.push_back(MusicRequest {
+                            uuid: Uuid::new_v4(),
+                            search_or_url: resolved.search_or_url,
+                            source: resolved.source,
+                            channel_id,
+                            requested_by,
+                            title: resolved.title,
+                            author: resolved.author,
+                            duration: resolved.duration,
+                            thumbnail: resolved.thumbnail,
+                        });
+                    };
+                    debug!("Inserted {} track(s) into guild {:?}", track_count, guild_music_data.guild_id);
+                }
+                InsertPosition::Front => {
+                    for resolved in resolved_tracks.into_iter().rev() {
+                        guild_music_data.queue.push_front(MusicRequest {
+                            uuid: Uuid::new_v4(),
+                            search_or_url: resolved.search_or_url,
+                            source: resolved.source,
+                            channel_id,
+                            requested_by,
+                            title: resolved.title,
+                            author: resolved.author,
+                            duration: resolved.duration,
+                            thumbnail: resolved.thumbnail,
+                        });
+                    };
+                    debug!("Inserted {} track(s) at the front of guild {:?}'s queue", track_count, guild_music_data.guild_id);
+                }
+            };
+            if track_count > 1 {
+                QueuedSummary::Playlist { track_count }
+            } else {
+                QueuedSummary::Track { title: first_title, position: queue_position }
+            }
+        };
+        self.tick_guild_music();
+        Ok(summary)
+    }
+
+    /// Queues a locally stored/downloaded file, bypassing `resolve` entirely since there's
+    /// no search/playlist step for something already sitting on disk.
+    pub fn insert_file(
+        &self,
+        path: PathBuf,
+        title: Option<String>,
+        channel_id: ChannelId,
+        requested_by: UserId,
+    ) -> GuildMusicResult<QueuedSummary> {
+        if !is_within_local_file_directory(&path) {
+            return Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::FileNotFound });
+        };
+        if !path.is_file() {
+            return Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::FileNotFound });
+        };
+        let search_or_url = path.display().to_string();
+        let display_title = title.clone().unwrap_or_else(|| search_or_url.clone());
+        let position = {
             let mut guild_music_data = self.guild_music_data.lock();
+            let position = guild_music_data.queue.len() + 1;
             guild_music_data.queue.push_back(MusicRequest {
                 uuid: Uuid::new_v4(),
                 search_or_url,
+                source: TrackSource::File(path),
                 channel_id,
-                _requested_by: requested_by,
-                _author: None,
-                _duration: None,
-                _title: None
+                requested_by,
+                title,
+                author: None,
+                duration: None,
+                thumbnail: None,
             });
-            debug!("Inserted music into guild {:?}", guild_music_data.guild_id);
-        }
+            position
+        };
         self.tick_guild_music();
+        Ok(QueuedSummary::Track { title: display_title, position })
     }
 
-    pub fn handle_track_event(&self, _ctx: &EventContext<'_>, music_uuid: Uuid) {
+    pub fn handle_track_event(&self, music_uuid: Uuid) {
         {
             let mut guild_music_data = self.guild_music_data.lock();
-            if let Some(now_playing) = &guild_music_data.now_playing {
-                if now_playing.uuid == music_uuid {
-                    if now_playing.track_handle.stop().is_err() {};
-                    guild_music_data.now_playing = None;
+            if guild_music_data.now_playing.as_ref().map_or(false, |now_playing| now_playing.request.uuid == music_uuid) {
+                let finished = guild_music_data.now_playing.take().expect("checked above").request;
+                match guild_music_data.repeat_mode {
+                    RepeatMode::Off => {},
+                    RepeatMode::Track => guild_music_data.queue.push_front(finished),
+                    RepeatMode::Queue => guild_music_data.queue.push_back(finished),
                 };
-            }
+            };
         }
         self.tick_guild_music();
     }
 
-    pub fn stop(&self) -> GuildMusicResult<()> {
+    pub fn cycle_repeat_mode(&self) -> RepeatMode {
         let mut guild_music_data = self.guild_music_data.lock();
-        if let Some(now_playing) = guild_music_data.now_playing.take() {
+        guild_music_data.repeat_mode = guild_music_data.repeat_mode.next();
+        guild_music_data.repeat_mode
+    }
+
+    pub fn repeat_mode(&self) -> RepeatMode {
+        self.guild_music_data.lock().repeat_mode
+    }
+
+    pub async fn stop(&self) -> GuildMusicResult<()> {
+        let (guild_id, was_playing) = {
+            let mut guild_music_data = self.guild_music_data.lock();
             guild_music_data.queue = VecDeque::new();
-            if let Err(why) = now_playing.track_handle.stop() {
-                return Err(DiscordCommandError { source: Some(Box::new(why)), severity: super::ErrorSeverity::Internal, kind: super::ErrorKind::InternalError })
-            };
+            (guild_music_data.guild_id, guild_music_data.now_playing.take().is_some())
+        };
+        if was_playing {
+            self.backend.stop(guild_id).await;
             Ok(())
         } else {
             Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::NoSongCurrentlyPlaying})
@@ -254,35 +402,31 @@ impl GuildMusic {
     }
 
     // maybe clean and or improve this logic up a bit
-    pub fn skip(&self, quantity: usize) -> GuildMusicResult<usize> {
-        let mut guild_music_data = self.guild_music_data.lock();
-        let queue_len = guild_music_data.queue.len();
-        let removed_from_queue = { 
-            let mut to_remove_from_queue = 0;
-            if quantity > 1 && queue_len > 0 {
-                to_remove_from_queue = {
-                    if quantity - 1 > guild_music_data.queue.len() {
-                        guild_music_data.queue.len()
-                    } else {
-                        quantity - 1
-                    }
+    pub async fn skip(&self, quantity: usize) -> GuildMusicResult<usize> {
+        let (guild_id, was_playing, removed_from_queue) = {
+            let mut guild_music_data = self.guild_music_data.lock();
+            let queue_len = guild_music_data.queue.len();
+            let removed_from_queue = {
+                let mut to_remove_from_queue = 0;
+                if quantity > 1 && queue_len > 0 {
+                    to_remove_from_queue = {
+                        if quantity - 1 > guild_music_data.queue.len() {
+                            guild_music_data.queue.len()
+                        } else {
+                            quantity - 1
+                        }
+                    };
+                    guild_music_data.queue = guild_music_data.queue.split_off(to_remove_from_queue);
                 };
-                guild_music_data.queue = guild_music_data.queue.split_off(to_remove_from_queue);
+                to_remove_from_queue
             };
-            to_remove_from_queue
+            (guild_music_data.guild_id, guild_music_data.now_playing.take().is_some(), removed_from_queue)
         };
-        if let Some(now_playing) = &guild_music_data.now_playing {
-            if let Err(why) = now_playing.track_handle.stop() {
-                Err( DiscordCommandError {
-                    kind: super::ErrorKind::InternalError,
-                    source: Some(Box::new(why)),
-                    severity: super::ErrorSeverity::Internal
-                })
-            } else {
-                Ok(removed_from_queue + 1)
-            }
+        if was_playing {
+            self.backend.stop(guild_id).await;
+            Ok(removed_from_queue + 1)
         } else {
-            Err( DiscordCommandError { 
+            Err( DiscordCommandError {
                 kind: super::ErrorKind::NoSongCurrentlyPlaying,
                 source: None,
                 severity: super::ErrorSeverity::UserInput
@@ -299,6 +443,76 @@ impl GuildMusic {
             Ok(())
         }
     }
+
+    pub fn list_queue(&self) -> Vec<QueueEntry> {
+        self.guild_music_data
+            .lock()
+            .queue
+            .iter()
+            .map(|music_request| QueueEntry {
+                title: music_request.display_name().to_string(),
+                requested_by: music_request.requested_by,
+                duration: music_request.duration,
+            })
+            .collect()
+    }
+
+    pub async fn now_playing(&self) -> GuildMusicResult<(ResolvedTrack, Option<Duration>)> {
+        let (guild_id, resolved) = {
+            let guild_music_data = self.guild_music_data.lock();
+            match &guild_music_data.now_playing {
+                Some(now_playing) => (guild_music_data.guild_id, now_playing.request.as_resolved_track()),
+                None => return Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::NoSongCurrentlyPlaying }),
+            }
+        };
+        let position = self.backend.position(guild_id).await;
+        Ok((resolved, position))
+    }
+
+    pub fn list_history(&self, limit: usize) -> Vec<store::HistoryEntry> {
+        self.store.recent_history(limit)
+    }
+
+    pub fn save_favorite(&self, user_id: UserId, name: String) -> GuildMusicResult<()> {
+        let current = {
+            let guild_music_data = self.guild_music_data.lock();
+            guild_music_data.now_playing.as_ref().map(|now_playing| {
+                (now_playing.request.search_or_url.clone(), now_playing.request.title.clone())
+            })
+        };
+        let (search_or_url, title) = current
+            .or_else(|| self.store.recent_history(1).into_iter().next().map(|entry| (entry.search_or_url, entry.title)))
+            .ok_or(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::NoSongCurrentlyPlaying })?;
+        self.store.save_favorite(user_id, name, search_or_url, title);
+        Ok(())
+    }
+
+    pub async fn play_favorite(
+        &self,
+        user_id: UserId,
+        name: &str,
+        channel_id: ChannelId,
+        requested_by: UserId,
+    ) -> GuildMusicResult<QueuedSummary> {
+        let favorite = self
+            .store
+            .get_favorite(user_id, name)
+            .ok_or(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::FavoriteNotFound })?;
+        self.insert_music(favorite.search_or_url, channel_id, requested_by).await
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    pub fn remove_at(&self, index: usize) -> GuildMusicResult<String> {
+        let mut guild_music_data = self.guild_music_data.lock();
+        if index < guild_music_data.queue.len() {
+            Ok(guild_music_data.queue.remove(index).expect("index was checked against queue length").search_or_url)
+        } else {
+            Err(DiscordCommandError { source: None, severity: super::ErrorSeverity::UserInput, kind: super::ErrorKind::QueueIndexOutOfBounds })
+        }
+    }
 }
 
 impl GuildMusicData {
@@ -306,9 +520,8 @@ impl GuildMusicData {
         Self {
             guild_id,
             queue: VecDeque::new(),
-            preloaded: None,
             now_playing: None,
-            call_pair: None,
+            repeat_mode: RepeatMode::default(),
         }
     }
 }
@@ -316,20 +529,28 @@ impl GuildMusicData {
 #[group]
 #[description = "music_group_description"]
 #[only_in("guilds")]
-#[commands(play, skip, stop, shuffle)]
+#[commands(play, playnext, skip, stop, shuffle, queue, remove, nowplaying, history, save, play_favorite_command, loop_command, play_file, backend_command)]
 pub struct Music;
 
-struct TrackEventListener {
-    guild_id: GuildId,
-    music_uuid: Uuid
+pub(crate) struct TrackEventListener {
+    pub(crate) guild_id: GuildId,
+    pub(crate) music_uuid: Uuid
 }
 
-#[async_trait]
-impl VoiceEventHandler for TrackEventListener {
-    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+impl TrackEventListener {
+    /// Dispatches to the matching `GuildMusic`, regardless of which backend noticed the
+    /// track end (songbird's global event, or a Lavalink websocket callback).
+    pub(crate) fn fire(&self) {
         if let Some(guild_music) = GUILD_MUSIC_HASHMAP.lock().get(&self.guild_id) {
-            guild_music.handle_track_event(ctx, self.music_uuid);
+            guild_music.handle_track_event(self.music_uuid);
         };
+    }
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEventListener {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        self.fire();
         None
     }
 }
@@ -363,6 +584,124 @@ async fn get_voice_guild_and_channel(
     None
 }
 
+/// Confirms `path` canonicalizes to somewhere inside `LOCAL_FILE_DIRECTORY`, so neither a
+/// `play-file` argument like `../../etc/passwd` nor a symlink planted in the directory can
+/// make the bot read or stream a file outside the sandboxed directory.
+fn is_within_local_file_directory(path: &Path) -> bool {
+    let base = match std::fs::canonicalize(LOCAL_FILE_DIRECTORY) {
+        Ok(base) => base,
+        Err(_) => return false,
+    };
+    std::fs::canonicalize(path).map_or(false, |canonical| canonical.starts_with(base))
+}
+
+/// Downloads a Discord attachment to `path`, creating its parent directory if needed, so it
+/// can be handed to symphonia the same way a locally dropped file would be.
+async fn download_attachment(attachment: &Attachment, path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    };
+    let bytes = attachment
+        .download()
+        .await
+        .map_err(|why| std::io::Error::new(std::io::ErrorKind::Other, why))?;
+    tokio::fs::write(path, bytes).await
+}
+
+/// Caps how much a `play-file <url>` download can pull in, so a link to an enormous file
+/// can't be used to exhaust disk space.
+const MAX_DOWNLOAD_URL_BYTES: u64 = 100 * 1024 * 1024;
+
+fn io_other_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(why: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, why)
+}
+
+/// Caps how many redirects `download_url` will follow, re-validating the target of each hop
+/// itself rather than letting reqwest chase `Location` headers unchecked.
+const MAX_DOWNLOAD_URL_REDIRECTS: u8 = 5;
+
+/// Rejects loopback/private/link-local/unspecified/unique-local addresses, so a
+/// `play-file <url>` can't be pointed at the bot's own internal network (e.g. a cloud
+/// metadata endpoint) to exfiltrate it into a voice channel. Checks the IPv6 unique-local
+/// and link-local ranges by hand rather than via `Ipv6Addr::is_unique_local`/`is_unicast_link_local`,
+/// which aren't available on every toolchain this crate is expected to build with.
+fn is_disallowed_ip(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast(),
+        std::net::IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            let is_unique_local = segments[0] & 0xfe00 == 0xfc00; // fc00::/7
+            let is_link_local = segments[0] & 0xffc0 == 0xfe80; // fe80::/10
+            ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || is_unique_local || is_link_local
+        }
+    }
+}
+
+/// Resolves `url`'s host, rejects it if every address it resolves to is disallowed, and
+/// returns one validated `SocketAddr`. Re-run on every redirect hop, since each hop's host
+/// can differ from the last.
+async fn resolve_allowed_address(url: &Url) -> std::io::Result<std::net::SocketAddr> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(io_other_error(format!("unsupported URL scheme: {}", url.scheme())));
+    };
+    let host = url.host_str().ok_or_else(|| io_other_error("URL has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    tokio::net::lookup_host((host, port))
+        .await?
+        .find(|socket_addr| !is_disallowed_ip(socket_addr.ip()))
+        .ok_or_else(|| io_other_error(format!("refusing to fetch from disallowed host {host}")))
+}
+
+/// Downloads a direct audio URL to `path` the same way `download_attachment` does for a
+/// Discord attachment, so a raw link can be played without going through yt-dlp.
+///
+/// Disables reqwest's automatic redirect handling and re-resolves/re-validates each hop by
+/// hand, pinning the connection to the exact address that passed validation (via
+/// `ClientBuilder::resolve`) so a DNS-rebinding attacker can't swap in a disallowed address
+/// between the validation lookup and the connection reqwest actually makes.
+async fn download_url(url: &Url, path: &Path) -> std::io::Result<()> {
+    let mut current = url.clone();
+    let mut response = None;
+    for _ in 0..=MAX_DOWNLOAD_URL_REDIRECTS {
+        let socket_addr = resolve_allowed_address(&current).await?;
+        let host = current.host_str().ok_or_else(|| io_other_error("URL has no host"))?.to_string();
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(&host, socket_addr)
+            .build()
+            .map_err(io_other_error)?;
+        let candidate = client.get(current.clone()).send().await.map_err(io_other_error)?;
+        if candidate.status().is_redirection() {
+            let location = candidate
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .ok_or_else(|| io_other_error("redirect response had no Location header"))?
+                .to_str()
+                .map_err(io_other_error)?;
+            current = current.join(location).map_err(io_other_error)?;
+            continue;
+        };
+        response = Some(candidate.error_for_status().map_err(io_other_error)?);
+        break;
+    }
+    let mut response = response.ok_or_else(|| io_other_error("too many redirects"))?;
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    };
+    if response.content_length().map_or(false, |length| length > MAX_DOWNLOAD_URL_BYTES) {
+        return Err(io_other_error("response exceeded the maximum download size"));
+    };
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(io_other_error)? {
+        if bytes.len() as u64 + chunk.len() as u64 > MAX_DOWNLOAD_URL_BYTES {
+            return Err(io_other_error("response exceeded the maximum download size"));
+        };
+        bytes.extend_from_slice(&chunk);
+    }
+    tokio::fs::write(path, bytes).await
+}
+
 #[command]
 #[aliases("p", "ply")]
 async fn play(context: &Context, message: &Message, mut args: Args) -> CommandResult {
@@ -371,12 +710,115 @@ async fn play(context: &Context, message: &Message, mut args: Args) -> CommandRe
         Some((guild_id, channel_id)) => {
             match args.single::<String>() {
                 Ok(argument) => {
-                    get_guild_music_for(context, guild_id).await.insert_music(
+                    if let Some(resource) = SpotifyClient::parse_resource(&argument) {
+                        return play_spotify_resource(context, message, guild_id, channel_id, requested_by, &resource).await;
+                    };
+                    match get_guild_music_for(context, guild_id).await.insert_music(
                         argument,
                         channel_id,
                         requested_by,
-                    );
-                    Ok(())
+                    ).await {
+                        Ok(QueuedSummary::Track { title, position }) => {
+                            reply_with_result(context, message, format!("Added {title} to queue (position {position})"), false).await;
+                            Ok(())
+                        },
+                        Ok(QueuedSummary::Playlist { track_count }) => {
+                            reply_with_result(context, message, format!("Queued {track_count} tracks from playlist"), false).await;
+                            Ok(())
+                        },
+                        Err(err) => Err(err.into()),
+                    }
+                },
+                Err(_) =>  {
+                    Err(DiscordCommandError {
+                        source: None,
+                        severity: super::ErrorSeverity::UserInput,
+                        kind: super::ErrorKind::MustProvideSomeArguments(1),
+                    }
+                    .into())
+                },
+            }
+        },
+        None => Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::NotInVoiceChannel,
+        }
+        .into()),
+    }
+}
+
+/// Expands a Spotify track/album/playlist link into one search query per track and queues
+/// each through the active backend's normal search path, since neither songbird nor Lavalink can
+/// stream Spotify directly.
+async fn play_spotify_resource(
+    context: &Context,
+    message: &Message,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    requested_by: UserId,
+    resource: &spotify::SpotifyResource,
+) -> CommandResult {
+    let client = SPOTIFY.as_ref().ok_or(DiscordCommandError {
+        source: None,
+        severity: super::ErrorSeverity::Internal,
+        kind: super::ErrorKind::SpotifyNotConfigured,
+    })?;
+    let queries = client.resolve_search_queries(resource).await.map_err(|why| {
+        warn!("Failed to resolve Spotify resource: {:?}", why);
+        DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::Internal,
+            kind: super::ErrorKind::BackendUnavailable,
+        }
+    })?;
+
+    let guild_music = get_guild_music_for(context, guild_id).await;
+    let mut queued = 0usize;
+    for query in queries {
+        if guild_music.insert_music(query, channel_id, requested_by).await.is_ok() {
+            queued += 1;
+        };
+    };
+    if queued == 0 {
+        return Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::NoMatchesFound,
+        }
+        .into());
+    };
+    if queued > 1 {
+        reply_with_result(context, message, format!("Queued {queued} tracks from Spotify"), false).await;
+    } else {
+        reply_with_result(context, message, "Added 1 track from Spotify to queue".to_string(), false).await;
+    };
+    Ok(())
+}
+
+#[command]
+#[aliases("pn")]
+async fn playnext(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let requested_by = message.author.id;
+    match get_voice_guild_and_channel(context, message).await {
+        Some((guild_id, channel_id)) => {
+            match args.single::<String>() {
+                Ok(argument) => {
+                    match get_guild_music_for(context, guild_id).await.insert_music_next(
+                        argument,
+                        channel_id,
+                        requested_by,
+                    ).await {
+                        Ok(QueuedSummary::Track { title, .. }) => {
+                            reply_with_result(context, message, format!("Added {title} to play next"), false).await;
+                            Ok(())
+                        },
+                        Ok(QueuedSummary::Playlist { track_count }) => {
+                            reply_with_result(context, message, format!("Queued {track_count} tracks to play next"), false).await;
+                            Ok(())
+                        },
+                        Err(err) => Err(err.into()),
+                    }
                 },
                 Err(_) =>  {
                     Err(DiscordCommandError {
@@ -401,7 +843,7 @@ async fn play(context: &Context, message: &Message, mut args: Args) -> CommandRe
 #[aliases("stp")]
 async fn stop(context: &Context, message: &Message, mut _args: Args) -> CommandResult {
     if let Some(guild_id) = message.guild_id {
-        if let Err(err) = get_guild_music_for(context, guild_id).await.stop() {
+        if let Err(err) = get_guild_music_for(context, guild_id).await.stop().await {
             Err(err.into())
         } else {
             reply_with_result(context, message, "Stopped current song and cleared the queue!".to_string(), false).await;
@@ -422,7 +864,7 @@ async fn stop(context: &Context, message: &Message, mut _args: Args) -> CommandR
 async fn skip(context: &Context, message: &Message, mut args: Args) -> CommandResult {
     if let Some(guild_id) = message.guild_id {
         let quantity = args.single::<usize>().unwrap_or(1);
-        match get_guild_music_for(context, guild_id).await.skip(quantity) {
+        match get_guild_music_for(context, guild_id).await.skip(quantity).await {
             Ok(removed) => {
                 reply_with_result(context, message, format!("Successfully skipped {} song(s)!", removed), false).await;
                 Ok(())
@@ -457,4 +899,401 @@ async fn shuffle(context: &Context, message: &Message, mut _args: Args) -> Comma
         }
         .into())
     }
+}
+
+const QUEUE_PAGE_SIZE: usize = 10;
+
+#[command]
+#[aliases("q")]
+async fn queue(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        let page = args.single::<usize>().unwrap_or(1).max(1);
+        let entries = get_guild_music_for(context, guild_id).await.list_queue();
+        let page_count = ((entries.len() + QUEUE_PAGE_SIZE - 1) / QUEUE_PAGE_SIZE).max(1);
+        reply_with_embed(context, message, |embed| {
+            embed.title("Queue");
+            if entries.is_empty() {
+                embed.description("The queue is empty!");
+            } else {
+                let start = (page - 1).saturating_mul(QUEUE_PAGE_SIZE);
+                let description = entries
+                    .iter()
+                    .enumerate()
+                    .skip(start)
+                    .take(QUEUE_PAGE_SIZE)
+                    .map(|(index, entry)| {
+                        let duration = entry.duration.map_or_else(|| "Unknown".to_string(), format_duration);
+                        format!("{}. {} — <@{}> ({})", index + 1, entry.title, entry.requested_by, duration)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if description.is_empty() {
+                    embed.description(format!("Page {page} is past the end of the queue."));
+                } else {
+                    embed.description(description);
+                };
+                embed.footer(|footer| footer.text(format!("Page {page} of {page_count} — use !queue <page> to see more")));
+            };
+            embed
+        }).await;
+        Ok(())
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+const PROGRESS_BAR_SEGMENTS: usize = 20;
+
+fn format_duration(duration: Duration) -> String {
+    let total_secs = duration.as_secs();
+    format!("{:02}:{:02}", total_secs / 60, total_secs % 60)
+}
+
+fn progress_bar(position: Option<Duration>, total: Option<Duration>) -> String {
+    match (position, total) {
+        (Some(position), Some(total)) if !total.is_zero() => {
+            let filled = ((position.as_secs_f64() / total.as_secs_f64()) * PROGRESS_BAR_SEGMENTS as f64)
+                .round()
+                .min(PROGRESS_BAR_SEGMENTS as f64) as usize;
+            let bar: String = (0..PROGRESS_BAR_SEGMENTS).map(|i| if i < filled { '▰' } else { '▱' }).collect();
+            format!("{bar}\n{} / {}", format_duration(position), format_duration(total))
+        }
+        (Some(position), None) => format_duration(position),
+        _ => "Unknown progress".to_string(),
+    }
+}
+
+#[command]
+#[aliases("np")]
+async fn nowplaying(context: &Context, message: &Message, mut _args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        let guild_music = get_guild_music_for(context, guild_id).await;
+        match guild_music.now_playing().await {
+            Ok((resolved, position)) => {
+                let repeat_mode = guild_music.repeat_mode();
+                reply_with_embed(context, message, |embed| {
+                    embed.title(resolved.title.as_deref().unwrap_or(&resolved.search_or_url));
+                    if let Some(author) = &resolved.author {
+                        embed.field("Artist", author, true);
+                    };
+                    if let Some(thumbnail) = &resolved.thumbnail {
+                        embed.thumbnail(thumbnail);
+                    };
+                    embed.description(progress_bar(position, resolved.duration));
+                    if repeat_mode != RepeatMode::Off {
+                        embed.footer(|footer| footer.text(format!("Looping: {}", repeat_mode.label())));
+                    };
+                    embed
+                }).await;
+                Ok(())
+            },
+            Err(err) => Err(err.into()),
+        }
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+#[command]
+#[aliases("rm")]
+async fn remove(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        match args.single::<usize>() {
+            Ok(position) if position >= 1 => {
+                match get_guild_music_for(context, guild_id).await.remove_at(position - 1) {
+                    Ok(removed) => {
+                        reply_with_result(context, message, format!("Removed {removed} from the queue!"), false).await;
+                        Ok(())
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+            _ => Err(DiscordCommandError {
+                source: None,
+                severity: super::ErrorSeverity::UserInput,
+                kind: super::ErrorKind::MustProvideSomeArguments(1),
+            }
+            .into()),
+        }
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+#[command]
+#[aliases("hist")]
+async fn history(context: &Context, message: &Message, mut _args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        let entries = get_guild_music_for(context, guild_id).await.list_history(10);
+        let reply = if entries.is_empty() {
+            "No tracks have been played in this server yet!".to_string()
+        } else {
+            entries
+                .iter()
+                .map(|entry| format!(
+                    "{} — {}",
+                    entry.played_at.format("%Y-%m-%d %H:%M UTC"),
+                    entry.title.as_deref().unwrap_or(&entry.search_or_url),
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        reply_with_result(context, message, reply, false).await;
+        Ok(())
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+#[command]
+async fn save(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        match args.single::<String>() {
+            Ok(name) => {
+                match get_guild_music_for(context, guild_id).await.save_favorite(message.author.id, name.clone()) {
+                    Ok(()) => {
+                        reply_with_result(context, message, format!("Saved the current or last played track as favorite \"{name}\""), false).await;
+                        Ok(())
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+            Err(_) => Err(DiscordCommandError {
+                source: None,
+                severity: super::ErrorSeverity::UserInput,
+                kind: super::ErrorKind::MustProvideSomeArguments(1),
+            }
+            .into()),
+        }
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+/// Reports which `PlaybackBackend` is currently driving playback, so it's easy to confirm
+/// `MUSIC_BACKEND` took effect without digging through logs.
+#[command("backend")]
+async fn backend_command(context: &Context, message: &Message, mut _args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        let name = get_guild_music_for(context, guild_id).await.backend_name();
+        reply_with_result(context, message, format!("Currently playing through the {name} backend"), false).await;
+        Ok(())
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+#[command("loop")]
+#[aliases("repeat")]
+async fn loop_command(context: &Context, message: &Message, mut _args: Args) -> CommandResult {
+    if let Some(guild_id) = message.guild_id {
+        let mode = get_guild_music_for(context, guild_id).await.cycle_repeat_mode();
+        reply_with_result(context, message, format!("Now looping: {}", mode.label()), false).await;
+        Ok(())
+    } else {
+        Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MessageNotInGuildChannel,
+        }
+        .into())
+    }
+}
+
+#[command("play-fav")]
+#[aliases("playfav", "pf")]
+async fn play_favorite_command(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let requested_by = message.author.id;
+    match (get_voice_guild_and_channel(context, message).await, args.single::<String>()) {
+        (Some((guild_id, channel_id)), Ok(name)) => {
+            match get_guild_music_for(context, guild_id).await.play_favorite(requested_by, &name, channel_id, requested_by).await {
+                Ok(QueuedSummary::Track { title, position }) => {
+                    reply_with_result(context, message, format!("Added favorite {title} to queue (position {position})"), false).await;
+                    Ok(())
+                }
+                Ok(QueuedSummary::Playlist { track_count }) => {
+                    reply_with_result(context, message, format!("Queued {track_count} tracks from favorite"), false).await;
+                    Ok(())
+                }
+                Err(err) => Err(err.into()),
+            }
+        }
+        (None, _) => Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::NotInVoiceChannel,
+        }
+        .into()),
+        (_, Err(_)) => Err(DiscordCommandError {
+            source: None,
+            severity: super::ErrorSeverity::UserInput,
+            kind: super::ErrorKind::MustProvideSomeArguments(1),
+        }
+        .into()),
+    }
+}
+
+/// Queues a local file decoded through symphonia, sourced from a Discord message
+/// attachment, a direct audio URL (downloaded into `LOCAL_FILE_DIRECTORY` first), or a path
+/// on disk (e.g. something already dropped into `LOCAL_FILE_DIRECTORY` out of band).
+#[command("play-file")]
+#[aliases("playfile")]
+async fn play_file(context: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let requested_by = message.author.id;
+    let (guild_id, channel_id) = match get_voice_guild_and_channel(context, message).await {
+        Some(guild_and_channel) => guild_and_channel,
+        None => {
+            return Err(DiscordCommandError {
+                source: None,
+                severity: super::ErrorSeverity::UserInput,
+                kind: super::ErrorKind::NotInVoiceChannel,
+            }
+            .into())
+        }
+    };
+    let (path, title) = if let Some(attachment) = message.attachments.first() {
+        // `attachment.filename` is attacker-controlled and may contain path separators or
+        // `..` components; only its final component is safe to fold into a path we write to.
+        let safe_filename = Path::new(&attachment.filename)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "attachment".to_string());
+        let path = PathBuf::from(LOCAL_FILE_DIRECTORY).join(format!("{}-{safe_filename}", attachment.id));
+        if let Err(why) = download_attachment(attachment, &path).await {
+            return Err(DiscordCommandError {
+                source: Some(Box::new(why)),
+                severity: super::ErrorSeverity::Internal,
+                kind: super::ErrorKind::InternalError,
+            }
+            .into());
+        };
+        (path, Some(attachment.filename.clone()))
+    } else {
+        match args.single::<String>() {
+            Ok(argument) => match argument.parse::<Url>() {
+                Ok(url) => {
+                    let filename = url
+                        .path_segments()
+                        .and_then(|mut segments| segments.next_back())
+                        .filter(|name| !name.is_empty())
+                        .map(|name| Path::new(name).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "download".to_string()))
+                        .unwrap_or_else(|| "download".to_string());
+                    let path = PathBuf::from(LOCAL_FILE_DIRECTORY).join(format!("{}-{filename}", Uuid::new_v4()));
+                    if let Err(why) = download_url(&url, &path).await {
+                        return Err(DiscordCommandError {
+                            source: Some(Box::new(why)),
+                            severity: super::ErrorSeverity::Internal,
+                            kind: super::ErrorKind::InternalError,
+                        }
+                        .into());
+                    };
+                    (path, Some(filename))
+                }
+                Err(_) => (PathBuf::from(argument), None),
+            },
+            Err(_) => {
+                return Err(DiscordCommandError {
+                    source: None,
+                    severity: super::ErrorSeverity::UserInput,
+                    kind: super::ErrorKind::MustProvideSomeArguments(1),
+                }
+                .into())
+            }
+        }
+    };
+    match get_guild_music_for(context, guild_id).await.insert_file(path, title, channel_id, requested_by) {
+        Ok(QueuedSummary::Track { title, position }) => {
+            reply_with_result(context, message, format!("Added {title} to queue (position {position})"), false).await;
+            Ok(())
+        }
+        Ok(QueuedSummary::Playlist { .. }) => unreachable!("local files never expand into playlists"),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_disallowed_ip_rejects_private_loopback_and_link_local_ranges() {
+        let disallowed = [
+            "127.0.0.1",
+            "169.254.169.254", // cloud metadata endpoints live here
+            "10.0.0.1",
+            "192.168.1.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fc00::1",
+        ];
+        for ip in disallowed {
+            assert!(is_disallowed_ip(ip.parse().unwrap()), "{ip} should be disallowed");
+        }
+        let allowed = ["8.8.8.8", "2606:4700:4700::1111"];
+        for ip in allowed {
+            assert!(!is_disallowed_ip(ip.parse().unwrap()), "{ip} should be allowed");
+        }
+    }
+
+    fn test_music_request(uuid: Uuid) -> MusicRequest {
+        MusicRequest {
+            uuid,
+            search_or_url: "test".to_string(),
+            source: TrackSource::Url("test".to_string()),
+            channel_id: ChannelId(1),
+            requested_by: UserId(1),
+            title: None,
+            author: None,
+            duration: None,
+            thumbnail: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn skip_clears_now_playing_before_the_backend_stop_completes() {
+        let guild_music = GuildMusic::new(GuildId(1));
+        let uuid = Uuid::new_v4();
+        {
+            let mut guild_music_data = guild_music.guild_music_data.lock();
+            guild_music_data.now_playing = Some(NowPlaying { request: test_music_request(uuid) });
+            guild_music_data.repeat_mode = RepeatMode::Track;
+        }
+        guild_music.skip(1).await.expect("a track was playing");
+        // Simulates the backend's track-end callback for the skipped track arriving after
+        // `skip()` already returned; it must not find `now_playing` still set to it, or
+        // `RepeatMode::Track` would re-queue the track `!skip` was meant to advance past.
+        guild_music.handle_track_event(uuid);
+        assert!(guild_music.guild_music_data.lock().queue.is_empty());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serenity::{
+    model::id::{ChannelId, GuildId},
+    prelude::Mutex as AsyncMutex,
+};
+use songbird::{
+    create_player,
+    input::{File as SongbirdFile, Input, Restartable},
+    tracks::{Track, TrackHandle},
+    Call, Event, TrackEvent,
+};
+use url::Url;
+use uuid::Uuid;
+
+use crate::SONGBIRD;
+
+use super::{
+    backend::{BackendError, PlaybackBackend, ResolvedTrack, TrackSource},
+    TrackEventListener, MAX_PLAYLIST_TRACKS,
+};
+
+type TrackPair = (Track, TrackHandle);
+
+#[derive(Deserialize)]
+struct FlatPlaylistEntry {
+    id: String,
+    title: Option<String>,
+    url: Option<String>,
+    duration: Option<f64>,
+}
+
+/// Recognizes YouTube's `?list=` playlist links as well as SoundCloud's `/sets/` links,
+/// both of which `yt-dlp --flat-playlist` can expand the same way.
+fn is_playlist_url(search_or_url: &str) -> bool {
+    search_or_url
+        .parse::<Url>()
+        .map(|url| {
+            url.query_pairs().any(|(key, _)| key == "list")
+                || url
+                    .host_str()
+                    .map_or(false, |host| host == "soundcloud.com" || host.ends_with(".soundcloud.com"))
+                    && url.path().contains("/sets/")
+        })
+        .unwrap_or(false)
+}
+
+/// Expands a playlist URL into its member tracks via `yt-dlp --flat-playlist`, which is
+/// far cheaper than resolving every entry through `Restartable::ytdl`.
+async fn resolve_playlist(url: &str) -> Result<Vec<ResolvedTrack>, BackendError> {
+    let output = tokio::process::Command::new("yt-dlp")
+        .args(["--flat-playlist", "--dump-json", url])
+        .output()
+        .await
+        .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+    let tracks: Vec<ResolvedTrack> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<FlatPlaylistEntry>(line).ok())
+        .take(MAX_PLAYLIST_TRACKS)
+        .map(|entry| {
+            let search_or_url = entry.url.unwrap_or_else(|| format!("https://www.youtube.com/watch?v={}", entry.id));
+            ResolvedTrack {
+                source: TrackSource::Url(search_or_url.clone()),
+                search_or_url,
+                title: entry.title,
+                author: None,
+                duration: entry.duration.map(Duration::from_secs_f64),
+                thumbnail: None,
+            }
+        })
+        .collect();
+    if tracks.is_empty() {
+        Err(BackendError::NoMatches)
+    } else {
+        Ok(tracks)
+    }
+}
+
+#[derive(Default)]
+struct GuildState {
+    call: Option<Arc<AsyncMutex<Call>>>,
+    now_playing: Option<TrackHandle>,
+    preloaded: Option<(String, TrackPair)>,
+}
+
+/// Drives playback the way the bot always has: songbird decodes/streams audio in-process
+/// through a `Call`, sourced from `yt-dlp` via `Restartable`.
+#[derive(Default)]
+pub struct SongbirdBackend {
+    guilds: Mutex<HashMap<GuildId, GuildState>>,
+}
+
+/// Keys the preload cache regardless of whether the track comes from yt-dlp or a local file.
+fn source_key(source: &TrackSource) -> String {
+    match source {
+        TrackSource::Url(search_or_url) => search_or_url.clone(),
+        TrackSource::File(path) => path.display().to_string(),
+    }
+}
+
+/// Builds a playable `TrackPair` for either a yt-dlp URL/search term or a local file decoded
+/// through symphonia (aac/mp3/isomp4/alac), so the rest of the backend doesn't need to care
+/// which source produced it.
+async fn get_track_pair(source: &TrackSource) -> Option<TrackPair> {
+    let input: Input = match source {
+        TrackSource::Url(search_or_url) => {
+            let restartable = if let Ok(url) = search_or_url.parse::<Url>() {
+                Restartable::ytdl(url, false).await
+            } else {
+                Restartable::ytdl_search(search_or_url, false).await
+            };
+            restartable.ok()?.into()
+        }
+        TrackSource::File(path) => SongbirdFile::new(path.clone()).into(),
+    };
+    Some(create_player(input))
+}
+
+fn as_ytdl_query(search_or_url: &str) -> String {
+    if search_or_url.parse::<Url>().is_ok() {
+        search_or_url.to_string()
+    } else {
+        format!("ytsearch1:{search_or_url}")
+    }
+}
+
+async fn get_call(guild_id: GuildId, channel_id: ChannelId) -> Option<Arc<AsyncMutex<Call>>> {
+    let (call_lock, result) = SONGBIRD.join(guild_id, channel_id).await;
+    match result {
+        Ok(_channel) => Some(call_lock),
+        Err(err) => {
+            warn!(
+                "Error is {}, leave server {}, should reconnect {}",
+                err,
+                err.should_leave_server(),
+                err.should_reconnect_driver()
+            );
+            None
+        }
+    }
+}
+
+#[async_trait]
+impl PlaybackBackend for SongbirdBackend {
+    fn name(&self) -> &'static str {
+        "songbird"
+    }
+
+    async fn resolve(&self, search_or_url: &str) -> Result<Vec<ResolvedTrack>, BackendError> {
+        if is_playlist_url(search_or_url) {
+            return resolve_playlist(search_or_url).await;
+        }
+        let metadata = songbird::input::ytdl_metadata(&as_ytdl_query(search_or_url))
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+        Ok(vec![ResolvedTrack {
+            search_or_url: search_or_url.to_string(),
+            source: TrackSource::Url(search_or_url.to_string()),
+            title: metadata.title,
+            author: metadata.artist.or(metadata.channel),
+            duration: metadata.duration,
+            thumbnail: metadata.thumbnail,
+        }])
+    }
+
+    async fn play(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        resolved: &ResolvedTrack,
+        music_uuid: Uuid,
+    ) -> Result<(), BackendError> {
+        let key = source_key(&resolved.source);
+        let preloaded = {
+            let mut guilds = self.guilds.lock();
+            let state = guilds.entry(guild_id).or_default();
+            state.preloaded.take().and_then(|(preloaded_key, track_pair)| {
+                if preloaded_key == key {
+                    Some(track_pair)
+                } else {
+                    None
+                }
+            })
+        };
+        let (track, track_handle) = match preloaded {
+            Some(track_pair) => track_pair,
+            None => get_track_pair(&resolved.source)
+                .await
+                .ok_or(BackendError::NoMatches)?,
+        };
+
+        let existing_call = self.guilds.lock().get(&guild_id).and_then(|state| state.call.clone());
+        let call_lock = match existing_call {
+            Some(call_lock) => call_lock,
+            None => get_call(guild_id, channel_id)
+                .await
+                .ok_or(BackendError::NotInVoiceChannel)?,
+        };
+
+        {
+            let mut guilds = self.guilds.lock();
+            let state = guilds.entry(guild_id).or_default();
+            state.call = Some(call_lock.clone());
+            state.now_playing = Some(track_handle.clone());
+        }
+
+        let mut call = call_lock.lock().await;
+        call.play(track);
+        call.remove_all_global_events();
+        call.add_global_event(
+            Event::Track(TrackEvent::End),
+            TrackEventListener { guild_id, music_uuid },
+        );
+        Ok(())
+    }
+
+    async fn stop(&self, guild_id: GuildId) {
+        if let Some(now_playing) = self
+            .guilds
+            .lock()
+            .get_mut(&guild_id)
+            .and_then(|state| state.now_playing.take())
+        {
+            if now_playing.stop().is_err() {
+                debug!("Tried to stop a track for guild {} that had already ended", guild_id);
+            };
+        };
+    }
+
+    async fn position(&self, guild_id: GuildId) -> Option<std::time::Duration> {
+        let track_handle = self
+            .guilds
+            .lock()
+            .get(&guild_id)
+            .and_then(|state| state.now_playing.clone());
+        match track_handle {
+            Some(track_handle) => track_handle.get_info().await.ok().map(|info| info.position),
+            None => None,
+        }
+    }
+
+    async fn preload(&self, guild_id: GuildId, track: &ResolvedTrack) {
+        if let Some(track_pair) = get_track_pair(&track.source).await {
+            let mut guilds = self.guilds.lock();
+            let state = guilds.entry(guild_id).or_default();
+            state.preloaded = Some((source_key(&track.source), track_pair));
+        };
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::{Duration, Instant};
+
+use log::debug;
+use parking_lot::Mutex;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use std::env;
+use url::Url;
+
+use super::backend::BackendError;
+use super::MAX_PLAYLIST_TRACKS;
+
+const SPOTIFY_CLIENT_ID_ENVIRONMENT_VARIABLE: &str = "SPOTIFY_CLIENT_ID";
+const SPOTIFY_CLIENT_SECRET_ENVIRONMENT_VARIABLE: &str = "SPOTIFY_CLIENT_SECRET";
+const SPOTIFY_TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const SPOTIFY_API_BASE: &str = "https://api.spotify.com/v1";
+
+/// Which kind of Spotify resource a `play` argument pointed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpotifyResourceKind {
+    Track,
+    Album,
+    Playlist,
+}
+
+impl SpotifyResourceKind {
+    fn from_path_segment(segment: &str) -> Option<Self> {
+        match segment {
+            "track" => Some(SpotifyResourceKind::Track),
+            "album" => Some(SpotifyResourceKind::Album),
+            "playlist" => Some(SpotifyResourceKind::Playlist),
+            _ => None,
+        }
+    }
+}
+
+/// A Spotify track/album/playlist identified in a `play` argument, not yet resolved to
+/// search queries.
+pub struct SpotifyResource {
+    kind: SpotifyResourceKind,
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct SpotifyArtist {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SpotifyTrack {
+    name: String,
+    artists: Vec<SpotifyArtist>,
+}
+
+impl SpotifyTrack {
+    fn search_query(&self) -> String {
+        let artists = self.artists.iter().map(|artist| artist.name.as_str()).collect::<Vec<_>>().join(", ");
+        format!("{artists} - {}", self.name)
+    }
+}
+
+#[derive(Deserialize)]
+struct SpotifyTrackPage {
+    items: Vec<SpotifyTrack>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SpotifyPlaylistItem {
+    track: SpotifyTrack,
+}
+
+#[derive(Deserialize)]
+struct SpotifyPlaylistTrackPage {
+    items: Vec<SpotifyPlaylistItem>,
+    next: Option<String>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+/// Resolves Spotify track/album/playlist links into `"<artist> - <title>"` search strings
+/// that flow into the active backend's existing search path, since songbird/yt-dlp can't stream
+/// Spotify directly. Authenticates with the client-credentials flow and caches the token
+/// until it's about to expire.
+pub struct SpotifyClient {
+    http: Client,
+    client_id: String,
+    client_secret: String,
+    token: Mutex<Option<CachedToken>>,
+}
+
+impl SpotifyClient {
+    pub fn from_env() -> Option<Self> {
+        let client_id = env::var(SPOTIFY_CLIENT_ID_ENVIRONMENT_VARIABLE).ok()?;
+        let client_secret = env::var(SPOTIFY_CLIENT_SECRET_ENVIRONMENT_VARIABLE).ok()?;
+        Some(Self { http: Client::new(), client_id, client_secret, token: Mutex::new(None) })
+    }
+
+    /// Recognizes `https://open.spotify.com/<kind>/<id>` links and `spotify:<kind>:<id>` URIs.
+    pub fn parse_resource(input: &str) -> Option<SpotifyResource> {
+        if let Some(rest) = input.strip_prefix("spotify:") {
+            let mut parts = rest.splitn(2, ':');
+            let kind = SpotifyResourceKind::from_path_segment(parts.next()?)?;
+            let id = parts.next()?.to_string();
+            return Some(SpotifyResource { kind, id });
+        };
+        let url = input.parse::<Url>().ok()?;
+        if url.host_str()? != "open.spotify.com" {
+            return None;
+        };
+        let mut segments = url.path_segments()?;
+        let kind = SpotifyResourceKind::from_path_segment(segments.next()?)?;
+        let id = segments.next()?.to_string();
+        Some(SpotifyResource { kind, id })
+    }
+
+    async fn access_token(&self) -> Result<String, BackendError> {
+        if let Some(token) = self.token.lock().as_ref() {
+            if token.expires_at > Instant::now() {
+                return Ok(token.access_token.clone());
+            };
+        };
+        let response = self
+            .http
+            .post(SPOTIFY_TOKEN_URL)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?
+            .json::<TokenResponse>()
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?;
+        let expires_at = Instant::now() + Duration::from_secs(response.expires_in.saturating_sub(30));
+        *self.token.lock() = Some(CachedToken { access_token: response.access_token.clone(), expires_at });
+        Ok(response.access_token)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str, token: &str) -> Result<T, BackendError> {
+        self.http
+            .get(url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))?
+            .json::<T>()
+            .await
+            .map_err(|why| BackendError::ConnectionFailed(why.to_string()))
+    }
+
+    /// Follows a Spotify paginated endpoint's `next` links until exhausted, applying
+    /// `extract_page` to turn each page into its tracks and the next URL to fetch. Stops
+    /// early and truncates at `MAX_PLAYLIST_TRACKS`, the same cap `resolve_playlist` in
+    /// `songbird_backend.rs` applies to yt-dlp playlist expansion.
+    async fn paginate_tracks<P: DeserializeOwned>(
+        &self,
+        mut url: String,
+        token: &str,
+        extract_page: impl Fn(P) -> (Vec<SpotifyTrack>, Option<String>),
+    ) -> Result<Vec<SpotifyTrack>, BackendError> {
+        let mut tracks = Vec::new();
+        loop {
+            let page = self.get_json::<P>(&url, token).await?;
+            let (mut page_tracks, next) = extract_page(page);
+            tracks.append(&mut page_tracks);
+            if tracks.len() >= MAX_PLAYLIST_TRACKS {
+                debug!("Truncating Spotify resource to {} tracks", MAX_PLAYLIST_TRACKS);
+                tracks.truncate(MAX_PLAYLIST_TRACKS);
+                break;
+            };
+            match next {
+                Some(next_url) => url = next_url,
+                None => break,
+            };
+        }
+        Ok(tracks)
+    }
+
+    /// Turns a resource into one search query per track (a single entry for `Track`, one per
+    /// member track for `Album`/`Playlist`).
+    pub async fn resolve_search_queries(&self, resource: &SpotifyResource) -> Result<Vec<String>, BackendError> {
+        let token = self.access_token().await?;
+        match resource.kind {
+            SpotifyResourceKind::Track => {
+                let track = self.get_json::<SpotifyTrack>(&format!("{SPOTIFY_API_BASE}/tracks/{}", resource.id), &token).await?;
+                Ok(vec![track.search_query()])
+            }
+            SpotifyResourceKind::Album => {
+                let tracks = self
+                    .paginate_tracks::<SpotifyTrackPage>(format!("{SPOTIFY_API_BASE}/albums/{}/tracks", resource.id), &token, |page| {
+                        (page.items, page.next)
+                    })
+                    .await?;
+                Ok(tracks.iter().map(SpotifyTrack::search_query).collect())
+            }
+            SpotifyResourceKind::Playlist => {
+                let tracks = self
+                    .paginate_tracks::<SpotifyPlaylistTrackPage>(format!("{SPOTIFY_API_BASE}/playlists/{}/tracks", resource.id), &token, |page| {
+                        (page.items.into_iter().map(|item| item.track).collect(), page.next)
+                    })
+                    .await?;
+                Ok(tracks.iter().map(SpotifyTrack::search_query).collect())
+            }
+        }
+    }
+}
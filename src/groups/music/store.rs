@@ -0,0 +1,88 @@
+use std::{collections::{HashMap, VecDeque}, fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, UserId};
+
+const STORE_DIRECTORY: &str = "music_data";
+const HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub search_or_url: String,
+    pub title: Option<String>,
+    pub played_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Favorite {
+    pub search_or_url: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GuildStoreData {
+    history: VecDeque<HistoryEntry>,
+    favorites: HashMap<UserId, HashMap<String, Favorite>>,
+}
+
+/// Persists a guild's play history and each user's saved favorites as JSON on disk, since
+/// the rest of `GuildMusicData` lives purely in memory and would otherwise lose this on
+/// every restart.
+pub struct MusicStore {
+    path: PathBuf,
+    data: Mutex<GuildStoreData>,
+}
+
+impl MusicStore {
+    pub fn load(guild_id: GuildId) -> Self {
+        let path = PathBuf::from(STORE_DIRECTORY).join(format!("{guild_id}.json"));
+        let data = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        Self { path, data: Mutex::new(data) }
+    }
+
+    fn save(&self, data: &GuildStoreData) {
+        if let Some(parent) = self.path.parent() {
+            if let Err(why) = fs::create_dir_all(parent) {
+                warn!("Failed to create music store directory: {}", why);
+                return;
+            };
+        };
+        match serde_json::to_string_pretty(data) {
+            Ok(serialized) => {
+                if let Err(why) = fs::write(&self.path, serialized) {
+                    warn!("Failed to persist music store at {:?}: {}", self.path, why);
+                };
+            }
+            Err(why) => warn!("Failed to serialize music store: {}", why),
+        };
+    }
+
+    pub fn record_played(&self, search_or_url: String, title: Option<String>) {
+        let mut data = self.data.lock();
+        data.history.push_back(HistoryEntry { search_or_url, title, played_at: Utc::now() });
+        while data.history.len() > HISTORY_LIMIT {
+            data.history.pop_front();
+        };
+        self.save(&data);
+    }
+
+    pub fn recent_history(&self, limit: usize) -> Vec<HistoryEntry> {
+        self.data.lock().history.iter().rev().take(limit).cloned().collect()
+    }
+
+    pub fn save_favorite(&self, user_id: UserId, name: String, search_or_url: String, title: Option<String>) {
+        let mut data = self.data.lock();
+        data.favorites.entry(user_id).or_default().insert(name, Favorite { search_or_url, title });
+        self.save(&data);
+    }
+
+    pub fn get_favorite(&self, user_id: UserId, name: &str) -> Option<Favorite> {
+        self.data.lock().favorites.get(&user_id).and_then(|favorites| favorites.get(name)).cloned()
+    }
+}
@@ -15,7 +15,7 @@ mod utils;
 pub use constants::*;
 use songbird::Songbird;
 
-use crate::{groups::music::MUSIC_GROUP, utils::reply_with_result};
+use crate::{groups::music, groups::music::MUSIC_GROUP, utils::reply_with_result};
 
 lazy_static! {
     static ref SONGBIRD: Arc<Songbird> = Songbird::serenity();
@@ -51,6 +51,11 @@ async fn after(context: &Context, message: &Message, command_name: &str, command
 async fn main() {
     info!("CLIENT STARTING ==============");
     let token = env::var(TOKEN_ENVIRONMENT_VARIABLE).expect("Expected BOT_RUST_TOKEN to be set as a environment variable");
+    let http = serenity::http::Http::new(&token);
+    match http.get_current_application_info().await {
+        Ok(application_info) => music::init_backend(application_info.id.0, &token).await,
+        Err(error) => warn!("Failed to fetch application info, defaulting to the songbird backend: {}", error),
+    }
     let intents = GatewayIntents::all();
     let framework = StandardFramework::new()
         .configure(|c| c
@@ -2,7 +2,7 @@ use std::{sync::Arc};
 
 use log::warn;
 use parking_lot::Mutex;
-use serenity::{model::channel::Message, Result as SerenityResult, client::Context, prelude::ModelError, Error as SerenityError};
+use serenity::{builder::CreateEmbed, model::channel::Message, Result as SerenityResult, client::Context, prelude::ModelError, Error as SerenityError};
 
 pub type ArcMut<T> = Arc<Mutex<T>>;
 
@@ -39,4 +39,21 @@ pub async fn reply_with_result(context: &Context, message: &Message, result: Str
             Ok(_) => {},
         };
     };
+}
+
+/// Like `reply_with_result`, but for responses that need more structure than a single
+/// line of text (track metadata, a progress bar, a thumbnail).
+pub async fn reply_with_embed(
+    context: &Context,
+    message: &Message,
+    build_embed: impl FnOnce(&mut CreateEmbed) -> &mut CreateEmbed,
+) {
+    let result = message
+        .channel_id
+        .send_message(context, |create_message| {
+            create_message.reference_message(message);
+            create_message.embed(build_embed)
+        })
+        .await;
+    handle_message_result(result);
 }
\ No newline at end of file